@@ -1,12 +1,17 @@
 // Code adapted from: https://github.com/tomusdrw/rust-web3/blob/master/src/api/accounts.rs
 use crate::{
-    types::{Address, PublicKey, H256},
-    utils::hash_message,
+    types::{Address, PublicKey, H256, U256},
+    utils::{hash_message, keccak256},
 };
 
 use rustc_hex::{FromHex, ToHex};
 use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, fmt, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+};
 
 use thiserror::Error;
 
@@ -37,8 +42,38 @@ pub enum SignatureError {
     /// Error in recovering public key from signature
     #[error("Public key recovery error")]
     RecoveryError,
+    /// Thrown when a signature's `s` value is not canonical, i.e. greater
+    /// than half the secp256k1 curve order, see EIP-2
+    #[error("signature has a non-canonical high S value")]
+    HighS,
+    /// Thrown when EIP-712 typed data references a struct type that was not
+    /// declared in its `types` map
+    #[error("missing EIP-712 type definition for `{0}`")]
+    MissingEip712Type(String),
+    /// Thrown when an EIP-712 message value doesn't match the type declared
+    /// for it (e.g. a non-numeric value for a `uint256` field)
+    #[error("invalid EIP-712 value for `{0}`")]
+    InvalidEip712Value(String),
+    /// Thrown when EIP-712 type or value nesting (e.g. `uint256[][]...` or
+    /// deeply nested struct fields) exceeds [`MAX_EIP712_DEPTH`]
+    #[error("EIP-712 type nesting exceeds the maximum depth of {0}")]
+    Eip712NestingTooDeep(usize),
 }
 
+/// The maximum nesting depth allowed when resolving EIP-712 type dependencies
+/// or encoding field values. Guards against a stack overflow from a
+/// maliciously deep array/struct type (e.g. `uint256[][][]...`) in
+/// wallet-supplied typed data, which is untrusted input.
+const MAX_EIP712_DEPTH: usize = 32;
+
+/// The order of the secp256k1 curve, `n`.
+const SECP256K1_N: U256 = U256([
+    0xBFD2_5E8C_D036_4141,
+    0xBAAE_DCE6_AF48_A03B,
+    0xFFFF_FFFF_FFFF_FFFE,
+    0xFFFF_FFFF_FFFF_FFFF,
+]);
+
 /// Recovery message data.
 ///
 /// The message data can either be a binary message that is first hashed
@@ -50,6 +85,8 @@ pub enum RecoveryMessage {
     Data(Vec<u8>),
     /// Message hash
     Hash(H256),
+    /// EIP-712 typed structured data, hashed via its `encode_eip712` digest
+    TypedData(TypedData),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -91,6 +128,63 @@ impl Signature {
     /// Recovery signature data uses 'Electrum' notation, this means the `v`
     /// value is expected to be either `27` or `28`.
     pub fn recover<M>(&self, message: M) -> Result<Address, SignatureError>
+    where
+        M: Into<RecoveryMessage>,
+    {
+        Ok(self.recover_verifying_key(message)?.into())
+    }
+
+    /// Recovers the public key which was used to sign the given message.
+    ///
+    /// This is the lower-level counterpart of [`Signature::recover`], useful
+    /// for callers that need the key itself (e.g. ECDH key agreement, or
+    /// caching a sender's public key) rather than just the address derived
+    /// from it.
+    pub fn recover_verifying_key<M>(&self, message: M) -> Result<PublicKey, SignatureError>
+    where
+        M: Into<RecoveryMessage>,
+    {
+        let encoded_point = self.recover_encoded_point(message)?;
+        Ok(PublicKey::from(encoded_point))
+    }
+
+    /// Recovers the public key which was used to sign the given message and
+    /// serializes it in SEC1 compressed form: a single parity-indicating
+    /// byte followed by the 32-byte x-coordinate.
+    pub fn recover_verifying_key_compressed<M>(
+        &self,
+        message: M,
+    ) -> Result<[u8; 33], SignatureError>
+    where
+        M: Into<RecoveryMessage>,
+    {
+        let encoded_point = self.recover_encoded_point(message)?;
+        let compressed = encoded_point.compress();
+        let mut buf = [0u8; 33];
+        buf.copy_from_slice(compressed.as_bytes());
+        Ok(buf)
+    }
+
+    /// Recovers the public key which was used to sign the given message and
+    /// serializes it in SEC1 uncompressed form: a `0x04` prefix followed by
+    /// the 32-byte x- and y-coordinates.
+    pub fn recover_verifying_key_uncompressed<M>(
+        &self,
+        message: M,
+    ) -> Result<[u8; 65], SignatureError>
+    where
+        M: Into<RecoveryMessage>,
+    {
+        let encoded_point = self.recover_encoded_point(message)?;
+        let mut buf = [0u8; 65];
+        buf.copy_from_slice(encoded_point.as_bytes());
+        Ok(buf)
+    }
+
+    /// Recovers the uncompressed SEC1-encoded public key point, doing the
+    /// actual signature recovery and hashing. All of the `recover*` methods
+    /// above share this single recovery path.
+    fn recover_encoded_point<M>(&self, message: M) -> Result<K256PublicKey, SignatureError>
     where
         M: Into<RecoveryMessage>,
     {
@@ -98,6 +192,7 @@ impl Signature {
         let message_hash = match message {
             RecoveryMessage::Data(ref message) => hash_message(message),
             RecoveryMessage::Hash(hash) => hash,
+            RecoveryMessage::TypedData(ref typed_data) => typed_data.encode_eip712()?,
         };
 
         let (recoverable_sig, _recovery_id) = self.as_signature()?;
@@ -106,8 +201,7 @@ impl Signature {
 
         let uncompressed_pub_key = K256PublicKey::from(&verify_key).decompress();
         if uncompressed_pub_key.is_some().into() {
-            let pub_key: K256PublicKey = K256PublicKey::from(uncompressed_pub_key.unwrap());
-            Ok(PublicKey::from(pub_key).into())
+            Ok(K256PublicKey::from(uncompressed_pub_key.unwrap()))
         } else {
             Err(SignatureError::RecoveryError)
         }
@@ -136,6 +230,78 @@ impl Signature {
     pub fn to_vec(&self) -> Vec<u8> {
         self.into()
     }
+
+    /// Recovers the Ethereum address which signed the given EIP-712 typed
+    /// data, using its [`TypedData::encode_eip712`] digest.
+    pub fn recover_typed_data(&self, typed_data: &TypedData) -> Result<Address, SignatureError> {
+        self.recover(typed_data.clone())
+    }
+
+    /// Recovers the Ethereum address which was used to sign the given
+    /// message, rejecting signatures whose `s` value is not canonical (see
+    /// [EIP-2]) rather than silently accepting them.
+    ///
+    /// [EIP-2]: https://eips.ethereum.org/EIPS/eip-2
+    pub fn recover_strict<M>(&self, message: M) -> Result<Address, SignatureError>
+    where
+        M: Into<RecoveryMessage>,
+    {
+        if !self.is_low_s() {
+            return Err(SignatureError::HighS);
+        }
+
+        self.recover(message)
+    }
+
+    /// Returns `true` if `s` is at most half the secp256k1 curve order, i.e.
+    /// this signature is already in canonical "low-S" form per [EIP-2].
+    ///
+    /// [EIP-2]: https://eips.ethereum.org/EIPS/eip-2
+    pub fn is_low_s(&self) -> bool {
+        U256::from_big_endian(self.s.as_bytes()) <= SECP256K1_N / 2
+    }
+
+    /// Normalizes `s` to its canonical "low-S" form and flips `v`'s
+    /// recovery-id parity to match, preventing the signature malleability
+    /// described in [EIP-2]. Does nothing if `s` is already canonical.
+    ///
+    /// [EIP-2]: https://eips.ethereum.org/EIPS/eip-2
+    pub fn normalize_s(&mut self) {
+        if !self.is_low_s() {
+            let s = SECP256K1_N - U256::from_big_endian(self.s.as_bytes());
+            let mut normalized_s = [0u8; 32];
+            s.to_big_endian(&mut normalized_s);
+            self.s = H256::from(normalized_s);
+            self.v = flip_recovery_id(self.v);
+        }
+    }
+
+    /// Returns a copy of this signature normalized to canonical "low-S" form.
+    /// See [`Signature::normalize_s`].
+    pub fn normalized(&self) -> Self {
+        let mut sig = self.clone();
+        sig.normalize_s();
+        sig
+    }
+}
+
+/// Flips the parity of the recovery id encoded in `v`, leaving any chain id
+/// it carries untouched.
+fn flip_recovery_id(v: u64) -> u64 {
+    match v {
+        0 => 1,
+        1 => 0,
+        27 => 28,
+        28 => 27,
+        v if v >= 35 => {
+            if (v - 35) % 2 == 0 {
+                v + 1
+            } else {
+                v - 1
+            }
+        }
+        v => v,
+    }
 }
 
 fn normalize_recovery_id(v: u64) -> u8 {
@@ -149,6 +315,418 @@ fn normalize_recovery_id(v: u64) -> u8 {
     }
 }
 
+/// An EIP-155 chain-replay-aware signature, used for recovering the signer of
+/// a message without losing the chain id that may be encoded in `v`.
+///
+/// Unlike [`Signature::recover`], which reduces `v` to a bare recovery id and
+/// ignores any chain id it carries, `Recovery` stores `v` in its EIP-155 form
+/// (see [`Recovery::new`]), so the chain a signature was meant for can be read
+/// back with [`Recovery::chain_id`] and checked against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recovery {
+    /// The original message
+    pub message: RecoveryMessage,
+    /// The V value of the signature
+    pub v: u64,
+    /// The R value of the signature
+    pub r: H256,
+    /// The S value of the signature
+    pub s: H256,
+}
+
+impl Recovery {
+    /// Creates a new `Recovery` from a message, an existing [`Signature`] and
+    /// an optional chain id.
+    ///
+    /// If `chain_id` is provided, `v` is encoded per [EIP-155]'s replay
+    /// protection scheme: `v = recovery_id + chain_id * 2 + 35`. Otherwise,
+    /// `v` is encoded in "Electrum" notation (`v = recovery_id + 27`).
+    ///
+    /// [EIP-155]: https://eips.ethereum.org/EIPS/eip-155
+    pub fn new<M>(message: M, signature: &Signature, chain_id: Option<u64>) -> Self
+    where
+        M: Into<RecoveryMessage>,
+    {
+        let recovery_id = normalize_recovery_id(signature.v) as u64;
+        let v = match chain_id {
+            Some(chain_id) => recovery_id + 35 + chain_id * 2,
+            None => recovery_id + 27,
+        };
+
+        Self { message: message.into(), v, r: signature.r, s: signature.s }
+    }
+
+    /// Decodes the recovery id encoded in `v`, ignoring any chain id.
+    pub fn recovery_id(&self) -> u8 {
+        match self.v {
+            0 | 1 => self.v as u8,
+            27 | 28 => (self.v - 27) as u8,
+            v if v >= 35 => ((v - 35) % 2) as u8,
+            _ => 4,
+        }
+    }
+
+    /// The chain id the signature was produced for, if `v` encodes one per
+    /// [EIP-155].
+    ///
+    /// [EIP-155]: https://eips.ethereum.org/EIPS/eip-155
+    pub fn chain_id(&self) -> Option<u64> {
+        match self.v {
+            v if v >= 35 => Some((v - 35) / 2),
+            _ => None,
+        }
+    }
+
+    /// Returns the standardized `(r, s, recovery_id)` form of this signature,
+    /// discarding the chain id, so it can be recovered the same way
+    /// regardless of whether it was replay-protected.
+    pub fn as_signature(&self) -> Signature {
+        Signature { r: self.r, s: self.s, v: self.recovery_id() as u64 + 27 }
+    }
+
+    /// Recovers the Ethereum address which signed `self.message`, using the
+    /// standardized form returned by [`Recovery::as_signature`].
+    pub fn recover(&self) -> Result<Address, SignatureError> {
+        self.as_signature().recover(self.message.clone())
+    }
+}
+
+/// The EIP-712 domain separator fields, as described in the [spec]. Only the
+/// fields that are `Some` are included in the computed `domainSeparator`.
+///
+/// [spec]: https://eips.ethereum.org/EIPS/eip-712
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EIP712Domain {
+    /// The user readable name of signing domain, i.e. the name of the DApp or the protocol.
+    pub name: Option<String>,
+    /// The current major version of the signing domain. Signatures from different versions
+    /// are not compatible.
+    pub version: Option<String>,
+    /// The EIP-155 chain id the signing domain is bound to, preventing replay across chains.
+    pub chain_id: Option<U256>,
+    /// The address of the contract that will verify the signature.
+    pub verifying_contract: Option<Address>,
+}
+
+impl EIP712Domain {
+    fn as_typed_data(&self) -> (Vec<TypedDataField>, BTreeMap<String, serde_json::Value>) {
+        let mut fields = Vec::new();
+        let mut data = BTreeMap::new();
+
+        if let Some(name) = &self.name {
+            fields.push(TypedDataField { name: "name".to_owned(), r#type: "string".to_owned() });
+            data.insert("name".to_owned(), serde_json::Value::String(name.clone()));
+        }
+        if let Some(version) = &self.version {
+            fields
+                .push(TypedDataField { name: "version".to_owned(), r#type: "string".to_owned() });
+            data.insert("version".to_owned(), serde_json::Value::String(version.clone()));
+        }
+        if let Some(chain_id) = &self.chain_id {
+            fields.push(TypedDataField {
+                name: "chainId".to_owned(),
+                r#type: "uint256".to_owned(),
+            });
+            data.insert("chainId".to_owned(), serde_json::Value::String(chain_id.to_string()));
+        }
+        if let Some(verifying_contract) = &self.verifying_contract {
+            fields.push(TypedDataField {
+                name: "verifyingContract".to_owned(),
+                r#type: "address".to_owned(),
+            });
+            data.insert(
+                "verifyingContract".to_owned(),
+                serde_json::Value::String(format!("{:?}", verifying_contract)),
+            );
+        }
+
+        (fields, data)
+    }
+
+    /// Computes this domain's EIP-712 `domainSeparator`.
+    fn separator(&self) -> Result<H256, SignatureError> {
+        let (fields, data) = self.as_typed_data();
+        let mut types = BTreeMap::new();
+        types.insert("EIP712Domain".to_owned(), fields);
+        hash_eip712_struct("EIP712Domain", &types, &data, 0)
+    }
+}
+
+/// A single member of an EIP-712 struct type, e.g. `{ name: "wallet", type:
+/// "address" }`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TypedDataField {
+    /// The field's name
+    pub name: String,
+    /// The field's Solidity type, either a primitive (`address`, `uint256`,
+    /// `bytes32`, ...) or the name of another entry in `TypedData::types`.
+    pub r#type: String,
+}
+
+/// EIP-712 structured data, as produced by wallets implementing
+/// `eth_signTypedData_v4`.
+///
+/// See the [spec] for the full encoding this type implements.
+///
+/// [spec]: https://eips.ethereum.org/EIPS/eip-712
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    /// The domain the signature is scoped to
+    pub domain: EIP712Domain,
+    /// All struct types referenced by `primary_type`, keyed by type name
+    pub types: BTreeMap<String, Vec<TypedDataField>>,
+    /// The name of the top-level type in `types` that `message` is an instance of
+    pub primary_type: String,
+    /// The message to be signed, as a map from field name to value
+    pub message: BTreeMap<String, serde_json::Value>,
+}
+
+impl TypedData {
+    /// Computes the final EIP-712 signing digest:
+    /// `keccak256(0x19 0x01 ‖ domainSeparator ‖ hashStruct(message))`.
+    pub fn encode_eip712(&self) -> Result<H256, SignatureError> {
+        let domain_separator = self.domain.separator()?;
+        let struct_hash = hash_eip712_struct(&self.primary_type, &self.types, &self.message, 0)?;
+
+        let mut bytes = Vec::with_capacity(2 + 32 + 32);
+        bytes.extend_from_slice(&[0x19, 0x01]);
+        bytes.extend_from_slice(domain_separator.as_bytes());
+        bytes.extend_from_slice(struct_hash.as_bytes());
+
+        Ok(H256(keccak256(&bytes)))
+    }
+}
+
+/// Recursively collects into `found` the name of `field_type` (with any `[]`
+/// suffixes stripped) and of every struct type it depends on, directly or
+/// transitively. Types that are not declared in `types` are not collected.
+fn find_eip712_type_dependencies<'a>(
+    field_type: &'a str,
+    types: &'a BTreeMap<String, Vec<TypedDataField>>,
+    found: &mut BTreeSet<&'a str>,
+    depth: usize,
+) -> Result<(), SignatureError> {
+    if depth > MAX_EIP712_DEPTH {
+        return Err(SignatureError::Eip712NestingTooDeep(MAX_EIP712_DEPTH));
+    }
+    let field_type = field_type.trim_end_matches("[]");
+    if !types.contains_key(field_type) || !found.insert(field_type) {
+        return Ok(());
+    }
+    for field in &types[field_type] {
+        find_eip712_type_dependencies(&field.r#type, types, found, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Builds the `encodeType` string for `primary_type`: its own member list
+/// followed by the member lists of its dependencies, sorted by name, per the
+/// [spec].
+///
+/// [spec]: https://eips.ethereum.org/EIPS/eip-712
+fn encode_eip712_type(
+    primary_type: &str,
+    types: &BTreeMap<String, Vec<TypedDataField>>,
+) -> Result<String, SignatureError> {
+    let fields = types
+        .get(primary_type)
+        .ok_or_else(|| SignatureError::MissingEip712Type(primary_type.to_owned()))?;
+
+    let mut deps = BTreeSet::new();
+    for field in fields {
+        find_eip712_type_dependencies(&field.r#type, types, &mut deps, 0)?;
+    }
+    deps.remove(primary_type);
+    let mut deps: Vec<&str> = deps.into_iter().collect();
+    deps.sort_unstable();
+
+    let mut encoded = String::new();
+    for name in std::iter::once(primary_type).chain(deps) {
+        let members = types[name]
+            .iter()
+            .map(|field| format!("{} {}", field.r#type, field.name))
+            .collect::<Vec<_>>()
+            .join(",");
+        encoded.push_str(&format!("{}({})", name, members));
+    }
+
+    Ok(encoded)
+}
+
+fn eip712_type_hash(
+    primary_type: &str,
+    types: &BTreeMap<String, Vec<TypedDataField>>,
+) -> Result<H256, SignatureError> {
+    Ok(H256(keccak256(encode_eip712_type(primary_type, types)?.as_bytes())))
+}
+
+/// ABI-encodes a single field's value into its 32-byte word, recursing into
+/// `hashStruct` for nested struct fields, hashing the concatenated per-element
+/// encodings for array fields (of any element type, including nested arrays),
+/// and hashing dynamic `bytes`/`string` values, per the [spec].
+///
+/// [spec]: https://eips.ethereum.org/EIPS/eip-712
+fn encode_eip712_field(
+    field_type: &str,
+    value: &serde_json::Value,
+    types: &BTreeMap<String, Vec<TypedDataField>>,
+    depth: usize,
+) -> Result<[u8; 32], SignatureError> {
+    if depth > MAX_EIP712_DEPTH {
+        return Err(SignatureError::Eip712NestingTooDeep(MAX_EIP712_DEPTH));
+    }
+
+    if let Some(element_type) = field_type.strip_suffix("[]") {
+        let elements = value
+            .as_array()
+            .ok_or_else(|| SignatureError::InvalidEip712Value(field_type.to_owned()))?;
+        let mut encoded = Vec::with_capacity(32 * elements.len());
+        for element in elements {
+            encoded
+                .extend_from_slice(&encode_eip712_field(element_type, element, types, depth + 1)?);
+        }
+        return Ok(keccak256(&encoded));
+    }
+
+    if types.contains_key(field_type) {
+        let data = value
+            .as_object()
+            .ok_or_else(|| SignatureError::InvalidEip712Value(field_type.to_owned()))?
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<BTreeMap<_, _>>();
+        return Ok(hash_eip712_struct(field_type, types, &data, depth + 1)?.0);
+    }
+
+    let invalid = || SignatureError::InvalidEip712Value(field_type.to_owned());
+    let mut word = [0u8; 32];
+    match field_type {
+        "string" => word = keccak256(value.as_str().ok_or_else(invalid)?.as_bytes()),
+        "bytes" => word = keccak256(decode_eip712_bytes(value).map_err(|_| invalid())?),
+        "bool" => word[31] = value.as_bool().ok_or_else(invalid)? as u8,
+        "address" => {
+            let address: Address =
+                value.as_str().ok_or_else(invalid)?.parse().map_err(|_| invalid())?;
+            word[12..].copy_from_slice(address.as_bytes());
+        }
+        t if t.starts_with("uint") => {
+            parse_eip712_uint(value).ok_or_else(invalid)?.to_big_endian(&mut word);
+        }
+        t if t.starts_with("int") => {
+            parse_eip712_int(value).ok_or_else(invalid)?.to_big_endian(&mut word);
+        }
+        t if t.starts_with("bytes") => {
+            let n: usize =
+                t.strip_prefix("bytes").and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+            if n == 0 || n > 32 {
+                return Err(invalid());
+            }
+            let bytes = decode_eip712_bytes(value).map_err(|_| invalid())?;
+            if bytes.len() != n {
+                return Err(invalid());
+            }
+            word[..bytes.len()].copy_from_slice(&bytes);
+        }
+        _ => return Err(SignatureError::MissingEip712Type(field_type.to_owned())),
+    }
+
+    Ok(word)
+}
+
+fn decode_eip712_bytes(value: &serde_json::Value) -> Result<Vec<u8>, SignatureError> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| SignatureError::InvalidEip712Value("bytes".to_owned()))?
+        .trim_start_matches("0x");
+    s.from_hex::<Vec<u8>>().map_err(SignatureError::DecodingError)
+}
+
+fn parse_eip712_uint(value: &serde_json::Value) -> Option<U256> {
+    if let Some(n) = value.as_u64() {
+        return Some(U256::from(n));
+    }
+    let s = value.as_str()?;
+    match s.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(s).ok(),
+    }
+}
+
+/// Parses a JSON `intN` value into its 256-bit two's-complement
+/// representation, matching how Solidity encodes signed integers in ABI
+/// encoding.
+fn parse_eip712_int(value: &serde_json::Value) -> Option<U256> {
+    if let Some(n) = value.as_i64() {
+        return Some(if n < 0 {
+            eip712_twos_complement(U256::from(n.unsigned_abs()))
+        } else {
+            U256::from(n)
+        });
+    }
+    let s = value.as_str()?;
+    if let Some(magnitude) = s.strip_prefix('-') {
+        let magnitude = match magnitude.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok()?,
+            None => U256::from_dec_str(magnitude).ok()?,
+        };
+        return Some(eip712_twos_complement(magnitude));
+    }
+    match s.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(s).ok(),
+    }
+}
+
+/// Computes the 256-bit two's-complement encoding of `-magnitude`.
+fn eip712_twos_complement(magnitude: U256) -> U256 {
+    (!magnitude).overflowing_add(U256::one()).0
+}
+
+/// ABI-encodes each of `primary_type`'s fields from `data`, in declaration
+/// order, and concatenates the 32-byte words, implementing EIP-712's
+/// `encodeData`.
+fn encode_eip712_data(
+    primary_type: &str,
+    types: &BTreeMap<String, Vec<TypedDataField>>,
+    data: &BTreeMap<String, serde_json::Value>,
+    depth: usize,
+) -> Result<Vec<u8>, SignatureError> {
+    let fields = types
+        .get(primary_type)
+        .ok_or_else(|| SignatureError::MissingEip712Type(primary_type.to_owned()))?;
+
+    let mut encoded = Vec::with_capacity(32 * fields.len());
+    for field in fields {
+        let value = data
+            .get(&field.name)
+            .ok_or_else(|| SignatureError::InvalidEip712Value(field.name.clone()))?;
+        encoded.extend_from_slice(&encode_eip712_field(&field.r#type, value, types, depth)?);
+    }
+
+    Ok(encoded)
+}
+
+/// Concatenates a struct's `typeHash` with its ABI-encoded fields and hashes
+/// the result, implementing EIP-712's `hashStruct`.
+fn hash_eip712_struct(
+    primary_type: &str,
+    types: &BTreeMap<String, Vec<TypedDataField>>,
+    data: &BTreeMap<String, serde_json::Value>,
+    depth: usize,
+) -> Result<H256, SignatureError> {
+    let mut bytes = eip712_type_hash(primary_type, types)?.as_bytes().to_vec();
+    bytes.extend(encode_eip712_data(primary_type, types, data, depth)?);
+    Ok(H256(keccak256(&bytes)))
+}
+
+impl From<TypedData> for RecoveryMessage {
+    fn from(typed_data: TypedData) -> Self {
+        RecoveryMessage::TypedData(typed_data)
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for Signature {
     type Error = SignatureError;
 
@@ -247,6 +825,188 @@ impl From<H256> for RecoveryMessage {
 mod tests {
     use super::*;
     use crate::types::PrivateKey;
+    use serde_json::json;
+
+    #[test]
+    fn encode_eip712_mail_example() {
+        // test vector taken from the EIP-712 spec itself:
+        // https://eips.ethereum.org/EIPS/eip-712
+        let mut types = BTreeMap::new();
+        types.insert(
+            "Person".to_owned(),
+            vec![
+                TypedDataField { name: "name".to_owned(), r#type: "string".to_owned() },
+                TypedDataField { name: "wallet".to_owned(), r#type: "address".to_owned() },
+            ],
+        );
+        types.insert(
+            "Mail".to_owned(),
+            vec![
+                TypedDataField { name: "from".to_owned(), r#type: "Person".to_owned() },
+                TypedDataField { name: "to".to_owned(), r#type: "Person".to_owned() },
+                TypedDataField { name: "contents".to_owned(), r#type: "string".to_owned() },
+            ],
+        );
+
+        let typed_data = TypedData {
+            domain: EIP712Domain {
+                name: Some("Ether Mail".to_owned()),
+                version: Some("1".to_owned()),
+                chain_id: Some(U256::from(1)),
+                verifying_contract: Some(
+                    "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC".parse().unwrap(),
+                ),
+            },
+            types,
+            primary_type: "Mail".to_owned(),
+            message: vec![
+                (
+                    "from".to_owned(),
+                    json!({
+                        "name": "Cow",
+                        "wallet": "0xCD2a3d9f938E13CD947Ec05AbC7FE734Df8DD826",
+                    }),
+                ),
+                (
+                    "to".to_owned(),
+                    json!({
+                        "name": "Bob",
+                        "wallet": "0xbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbB",
+                    }),
+                ),
+                ("contents".to_owned(), json!("Hello, Bob!")),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let digest = typed_data.encode_eip712().unwrap();
+        assert_eq!(
+            format!("{:?}", digest),
+            "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+
+    #[test]
+    fn recover_typed_data_round_trips() {
+        let mut types = BTreeMap::new();
+        types.insert(
+            "Person".to_owned(),
+            vec![
+                TypedDataField { name: "name".to_owned(), r#type: "string".to_owned() },
+                TypedDataField { name: "wallet".to_owned(), r#type: "address".to_owned() },
+            ],
+        );
+
+        let key = PrivateKey::new(&mut rand::thread_rng());
+        let address = Address::from(&key);
+
+        let typed_data = TypedData {
+            domain: EIP712Domain {
+                name: Some("Test".to_owned()),
+                version: Some("1".to_owned()),
+                chain_id: Some(U256::from(1)),
+                verifying_contract: None,
+            },
+            types,
+            primary_type: "Person".to_owned(),
+            message: vec![
+                ("name".to_owned(), json!("Alice")),
+                ("wallet".to_owned(), json!(format!("{:?}", address))),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let digest = typed_data.encode_eip712().unwrap();
+        let signature = key.sign(digest);
+
+        assert_eq!(signature.recover_typed_data(&typed_data).unwrap(), address);
+    }
+
+    #[test]
+    fn recover_typed_data_with_array_field() {
+        let mut types = BTreeMap::new();
+        types.insert(
+            "Mail".to_owned(),
+            vec![
+                TypedDataField { name: "from".to_owned(), r#type: "address".to_owned() },
+                TypedDataField { name: "recipients".to_owned(), r#type: "address[]".to_owned() },
+                TypedDataField { name: "amounts".to_owned(), r#type: "uint256[]".to_owned() },
+            ],
+        );
+
+        let key = PrivateKey::new(&mut rand::thread_rng());
+        let address = Address::from(&key);
+
+        let typed_data = TypedData {
+            domain: EIP712Domain {
+                name: Some("Test".to_owned()),
+                version: Some("1".to_owned()),
+                chain_id: Some(U256::from(1)),
+                verifying_contract: None,
+            },
+            types,
+            primary_type: "Mail".to_owned(),
+            message: vec![
+                ("from".to_owned(), json!(format!("{:?}", address))),
+                (
+                    "recipients".to_owned(),
+                    json!([
+                        "0xbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbBbB",
+                        "0xCD2a3d9f938E13CD947Ec05AbC7FE734Df8DD826",
+                    ]),
+                ),
+                ("amounts".to_owned(), json!(["1", "2"])),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let digest = typed_data.encode_eip712().unwrap();
+        let signature = key.sign(digest);
+
+        assert_eq!(signature.recover_typed_data(&typed_data).unwrap(), address);
+    }
+
+    #[test]
+    fn encode_eip712_field_negative_int_is_twos_complement() {
+        // -1 as a two's-complement 256-bit word is all `0xff`s, regardless of
+        // the declared bit width of the `intN` type.
+        let word = encode_eip712_field("int8", &json!(-1), &BTreeMap::new(), 0).unwrap();
+        assert_eq!(word, [0xffu8; 32]);
+
+        let word = encode_eip712_field("int256", &json!("-1"), &BTreeMap::new(), 0).unwrap();
+        assert_eq!(word, [0xffu8; 32]);
+    }
+
+    #[test]
+    fn encode_eip712_field_rejects_bytesn_length_mismatch() {
+        // `bytes16` requires exactly 16 bytes; a 20-byte value must be
+        // rejected rather than silently left-packed into the word.
+        let value = json!(format!("0x{}", "00".repeat(20)));
+        let result = encode_eip712_field("bytes16", &value, &BTreeMap::new(), 0);
+        assert!(matches!(result, Err(SignatureError::InvalidEip712Value(_))));
+    }
+
+    #[test]
+    fn encode_eip712_field_rejects_excessive_array_nesting() {
+        // Build a `uint256[][]...[]` type and a matching, equally deep JSON
+        // array nested past `MAX_EIP712_DEPTH`, simulating a maliciously deep
+        // type from wallet-supplied `TypedData`.
+        let mut field_type = "uint256".to_owned();
+        let mut value = json!(1);
+        for _ in 0..(MAX_EIP712_DEPTH + 2) {
+            field_type.push_str("[]");
+            value = json!([value]);
+        }
+
+        let result = encode_eip712_field(&field_type, &value, &BTreeMap::new(), 0);
+        assert!(matches!(
+            result,
+            Err(SignatureError::Eip712NestingTooDeep(d)) if d == MAX_EIP712_DEPTH
+        ));
+    }
 
     #[test]
     fn recover_signature_from_message() {
@@ -293,6 +1053,70 @@ mod tests {
         assert_eq!(recovered, address);
     }
 
+    #[test]
+    fn recovery_round_trips_chain_id() {
+        let message = "Some data";
+        let key = PrivateKey::new(&mut rand::thread_rng());
+        let signature = key.sign(message);
+
+        let recovery = Recovery::new(message, &signature, Some(1));
+        assert_eq!(recovery.chain_id(), Some(1));
+        assert_eq!(recovery.recovery_id(), normalize_recovery_id(signature.v));
+        assert_eq!(recovery.as_signature(), signature);
+
+        let recovery_no_chain = Recovery::new(message, &signature, None);
+        assert_eq!(recovery_no_chain.chain_id(), None);
+        assert_eq!(recovery_no_chain.as_signature(), signature);
+    }
+
+    #[test]
+    fn recover_strict_rejects_malleable_twin_and_normalized_restores_it() {
+        let message = "Some data";
+        let key = PrivateKey::new(&mut rand::thread_rng());
+        let address = Address::from(&key);
+        let signature = key.sign(message);
+        assert!(signature.is_low_s());
+
+        // build the malleable high-S twin by hand
+        let s = U256::from_big_endian(signature.s.as_bytes());
+        let mut malleable_s = [0u8; 32];
+        (SECP256K1_N - s).to_big_endian(&mut malleable_s);
+        let malleable = Signature {
+            r: signature.r,
+            s: H256::from(malleable_s),
+            v: flip_recovery_id(signature.v),
+        };
+
+        assert!(!malleable.is_low_s());
+        // the malleable twin still recovers to the same address with the
+        // lenient `recover`...
+        assert_eq!(malleable.recover(message).unwrap(), address);
+        // ...but is rejected by the strict, canonical-only path
+        assert!(malleable.recover_strict(message).is_err());
+
+        // and normalizing it recovers the original, canonical signature
+        assert_eq!(malleable.normalized(), signature);
+    }
+
+    #[test]
+    fn recover_verifying_key_matches_address() {
+        let message = "Some data";
+        let key = PrivateKey::new(&mut rand::thread_rng());
+        let address = Address::from(&key);
+        let signature = key.sign(message);
+
+        let verifying_key = signature.recover_verifying_key(message).unwrap();
+        assert_eq!(Address::from(verifying_key), address);
+
+        let compressed = signature.recover_verifying_key_compressed(message).unwrap();
+        assert_eq!(compressed.len(), 33);
+        assert!(compressed[0] == 0x02 || compressed[0] == 0x03);
+
+        let uncompressed = signature.recover_verifying_key_uncompressed(message).unwrap();
+        assert_eq!(uncompressed.len(), 65);
+        assert_eq!(uncompressed[0], 0x04);
+    }
+
     #[test]
     fn to_vec() {
         let message = "Some data";